@@ -0,0 +1,217 @@
+//! On-disk cache for backend responses that are a pure function of their input. Synthesis of
+//! the same text/voice/params combination is common in TTS workflows, so caching lets repeat
+//! requests skip the backend round trip entirely.
+
+use crate::JsonRpcResponse;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Methods whose output is a deterministic function of their params and are therefore safe
+/// to serve from cache instead of forwarding to the backend.
+const CACHEABLE_METHODS: &[&str] = &["tts.synthesize"];
+
+/// Caps the number of cached responses; the least recently used entry is evicted once the
+/// cap is exceeded.
+const MAX_CACHE_ENTRIES: usize = 500;
+
+pub fn is_cacheable(method: &str) -> bool {
+    CACHEABLE_METHODS.contains(&method)
+}
+
+/// FNV-1a over raw bytes. Used instead of `std::collections::hash_map::DefaultHasher` for
+/// on-disk cache keys: `DefaultHasher`'s algorithm is explicitly not guaranteed stable across
+/// Rust compiler versions, which would silently invalidate the whole persisted cache on a
+/// toolchain bump with no way to detect it happened.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// Stable key over `method` + params: serde_json sorts object keys when serializing (the
+/// `preserve_order` feature isn't enabled here), so equal params always hash the same way
+/// regardless of the order the caller wrote them in.
+pub fn key_for(method: &str, params: &Option<serde_json::Value>) -> String {
+    let mut bytes = method.as_bytes().to_vec();
+    bytes.push(0);
+    bytes.extend_from_slice(serde_json::to_string(params).unwrap_or_default().as_bytes());
+    format!("{:016x}", fnv1a(&bytes))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub size_bytes: u64,
+}
+
+/// Tracks real recency for eviction: sled's key order is lexicographic over the hash-derived
+/// key and has nothing to do with access time, so a genuine least-recently-used index has to
+/// be kept alongside it. Rebuilt from scratch on each process start — an entry that hasn't
+/// been touched yet this session is treated as least recently used, which only matters for
+/// eviction ordering and self-corrects as entries are read or written.
+struct LruIndex {
+    next_seq: u64,
+    seq_to_key: BTreeMap<u64, String>,
+    key_to_seq: HashMap<String, u64>,
+}
+
+impl LruIndex {
+    fn new() -> Self {
+        Self {
+            next_seq: 0,
+            seq_to_key: BTreeMap::new(),
+            key_to_seq: HashMap::new(),
+        }
+    }
+
+    /// Marks `key` as just-used, moving it to the most-recent end of the index.
+    fn touch(&mut self, key: &str) {
+        self.remove(key);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.seq_to_key.insert(seq, key.to_string());
+        self.key_to_seq.insert(key.to_string(), seq);
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(seq) = self.key_to_seq.remove(key) {
+            self.seq_to_key.remove(&seq);
+        }
+    }
+
+    /// The key that was touched longest ago, if the index knows about any entries at all.
+    fn least_recently_used(&self) -> Option<String> {
+        self.seq_to_key.values().next().cloned()
+    }
+}
+
+pub struct ResponseCache {
+    db: sled::Db,
+    lru: Mutex<LruIndex>,
+}
+
+impl ResponseCache {
+    pub fn open(dir: &Path) -> sled::Result<Self> {
+        let db = sled::open(dir.join("response_cache"))?;
+        Ok(Self {
+            db,
+            lru: Mutex::new(LruIndex::new()),
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<JsonRpcResponse> {
+        let response = self
+            .db
+            .get(key)
+            .ok()
+            .flatten()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        if response.is_some() {
+            self.lru.lock().touch(key);
+        }
+        response
+    }
+
+    pub fn put(&self, key: &str, response: &JsonRpcResponse) {
+        if let Ok(bytes) = serde_json::to_vec(response) {
+            let _ = self.db.insert(key, bytes);
+            self.lru.lock().touch(key);
+            self.evict_if_needed();
+        }
+    }
+
+    fn evict_if_needed(&self) {
+        while self.db.len() > MAX_CACHE_ENTRIES {
+            // Entries left over from a prior session that were never touched this run rank as
+            // least recently used, so they go (in sled's own order) before any indexed entry.
+            let victim = {
+                let lru = self.lru.lock();
+                self.db
+                    .iter()
+                    .keys()
+                    .filter_map(Result::ok)
+                    .map(|key| String::from_utf8_lossy(&key).into_owned())
+                    .find(|key| !lru.key_to_seq.contains_key(key))
+                    .or_else(|| lru.least_recently_used())
+            };
+            match victim {
+                Some(key) => {
+                    let _ = self.db.remove(&key);
+                    self.lru.lock().remove(&key);
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        let _ = self.db.clear();
+        *self.lru.lock() = LruIndex::new();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.db.len(),
+            size_bytes: self.db.size_on_disk().unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_for_is_deterministic_and_order_independent() {
+        let a = key_for(
+            "tts.synthesize",
+            &Some(serde_json::json!({ "voice": "en-US", "text": "hi" })),
+        );
+        let b = key_for(
+            "tts.synthesize",
+            &Some(serde_json::json!({ "text": "hi", "voice": "en-US" })),
+        );
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn key_for_distinguishes_method_and_params() {
+        let base = key_for("tts.synthesize", &Some(serde_json::json!({ "text": "hi" })));
+        let other_method = key_for("tts.describe", &Some(serde_json::json!({ "text": "hi" })));
+        let other_params = key_for(
+            "tts.synthesize",
+            &Some(serde_json::json!({ "text": "bye" })),
+        );
+        assert_ne!(base, other_method);
+        assert_ne!(base, other_params);
+    }
+
+    #[test]
+    fn lru_index_evicts_least_recently_used_first() {
+        let mut lru = LruIndex::new();
+        lru.touch("a");
+        lru.touch("b");
+        lru.touch("c");
+        assert_eq!(lru.least_recently_used(), Some("a".to_string()));
+
+        lru.touch("a");
+        assert_eq!(lru.least_recently_used(), Some("b".to_string()));
+    }
+
+    #[test]
+    fn lru_index_forgets_removed_keys() {
+        let mut lru = LruIndex::new();
+        lru.touch("a");
+        lru.touch("b");
+        lru.remove("a");
+        assert_eq!(lru.least_recently_used(), Some("b".to_string()));
+    }
+}
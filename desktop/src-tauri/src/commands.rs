@@ -1,97 +1,285 @@
-use crate::{BackendState, JsonRpcRequest, JsonRpcResponse, JsonRpcError};
+use crate::cache::{self, ResponseCache};
+use crate::errors::{self, ErrorKind};
+use crate::streaming::StreamRegistry;
+use crate::{BackendState, CapabilitiesInfo, JsonRpcRequest, JsonRpcResponse};
+use std::time::Duration;
 use tauri::{AppHandle, Manager, State};
 use tokio::sync::oneshot;
-use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn error_response(id: Option<u64>, kind: ErrorKind, message: impl Into<String>) -> JsonRpcResponse {
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        result: None,
+        error: Some(errors::rpc_error(kind, message, None)),
+        id,
+    }
+}
+
+/// Whether `method` is allowed to reach the backend: true if the backend hasn't advertised a
+/// capability list yet (treated as "anything goes" since there's nothing to check against), or
+/// if `method` is in the list it negotiated. Split out of [`dispatch`] so the gating rule is
+/// unit-testable without a real `BackendState`.
+fn is_method_advertised(capabilities: &[String], method: &str) -> bool {
+    capabilities.is_empty() || capabilities.iter().any(|c| c == method)
+}
+
+/// Registers `request` in `pending_requests` and writes it to the backend, returning the
+/// receiver the caller awaits for the matching response. Used by both the single-request and
+/// batch paths of [`ipc_call`] so every request in a batch is in flight before any of them block.
+fn dispatch(
+    state: &State<'_, BackendState>,
+    request: &JsonRpcRequest,
+) -> oneshot::Receiver<JsonRpcResponse> {
+    let id = request.id;
+    let (tx, rx) = oneshot::channel();
+
+    if !is_method_advertised(&state.capabilities(), &request.method) {
+        let error = errors::rpc_error(
+            ErrorKind::MethodNotAdvertised,
+            format!("Backend did not advertise method: {}", request.method),
+            Some(serde_json::json!({ "method": request.method })),
+        );
+        let _ = tx.send(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            result: None,
+            error: Some(error),
+            id: Some(id),
+        });
+        return rx;
+    }
+
+    state.add_pending(id, tx);
+
+    let mut line = serde_json::to_string(request).unwrap();
+    line.push('\n');
+
+    if let Err(e) = state.write(line.as_bytes()) {
+        if let Some(sender) = state.take_pending(id) {
+            let _ = sender.send(error_response(Some(id), ErrorKind::Internal, e));
+        }
+        return rx;
+    }
+
+    rx
+}
+
+/// Awaits the response for a single in-flight request, translating timeout into a distinct
+/// error code so callers can tell it apart from a real backend-side failure or a cancellation.
+async fn await_response(
+    state: &State<'_, BackendState>,
+    id: u64,
+    rx: oneshot::Receiver<JsonRpcResponse>,
+) -> JsonRpcResponse {
+    match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(_)) => error_response(Some(id), ErrorKind::Cancelled, "Request cancelled"),
+        Err(_) => {
+            state.pending_requests.lock().remove(&id);
+            error_response(Some(id), ErrorKind::Timeout, "Request timeout")
+        }
+    }
+}
+
+/// Resolves a request to its cache key, or `None` if it isn't eligible for caching right now.
+fn cache_key_for(request: &JsonRpcRequest, bypass_cache: bool) -> Option<String> {
+    if bypass_cache || !cache::is_cacheable(&request.method) {
+        return None;
+    }
+    Some(cache::key_for(&request.method, &request.params))
+}
 
 #[tauri::command]
 pub async fn ipc_call(
     request_str: String,
+    bypass_cache: Option<bool>,
     state: State<'_, BackendState>,
+    cache: State<'_, Option<ResponseCache>>,
 ) -> Result<String, String> {
+    let bypass_cache = bypass_cache.unwrap_or(false);
+
     if !state.is_running() {
-        let error = state.get_error().unwrap_or_else(|| "Backend not running".to_string());
-        let response = JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            result: None,
-            error: Some(JsonRpcError {
-                code: -32603,
-                message: error,
-                data: None,
-            }),
-            id: None,
-        };
-        return Ok(serde_json::to_string(&response).unwrap());
-    }
-
-    let request: JsonRpcRequest = serde_json::from_str(&request_str).map_err(|e| {
-        let response = JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            result: None,
-            error: Some(JsonRpcError {
-                code: -32700,
-                message: format!("Parse error: {}", e),
-                data: None,
-            }),
-            id: None,
-        };
-        serde_json::to_string(&response).unwrap()
+        let error = state
+            .get_error()
+            .unwrap_or_else(|| "Backend not running".to_string());
+        let kind = state.error_kind().unwrap_or(ErrorKind::BackendNotRunning);
+        return Ok(serde_json::to_string(&error_response(None, kind, error)).unwrap());
+    }
+
+    if !state.is_handshake_ok() {
+        let error = state
+            .get_error()
+            .unwrap_or_else(|| "Backend handshake not yet complete".to_string());
+        return Ok(serde_json::to_string(&error_response(
+            None,
+            ErrorKind::HandshakeIncomplete,
+            error,
+        ))
+        .unwrap());
+    }
+
+    let value: serde_json::Value = serde_json::from_str(&request_str).map_err(|e| {
+        serde_json::to_string(&error_response(
+            None,
+            ErrorKind::ParseError,
+            format!("Parse error: {}", e),
+        ))
+        .unwrap()
     })?;
 
-    let id = request.id;
-    let (tx, rx) = oneshot::channel();
+    if let Some(entries) = value.as_array() {
+        let mut requests = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let request: JsonRpcRequest = serde_json::from_value(entry.clone()).map_err(|e| {
+                serde_json::to_string(&error_response(
+                    None,
+                    ErrorKind::ParseError,
+                    format!("Parse error: {}", e),
+                ))
+                .unwrap()
+            })?;
+            requests.push(request);
+        }
 
-    state.add_pending(id, tx);
+        enum Slot {
+            Cached(JsonRpcResponse),
+            Pending(u64, oneshot::Receiver<JsonRpcResponse>, Option<String>),
+        }
+
+        let mut slots = Vec::with_capacity(requests.len());
+        for request in &requests {
+            let key = cache_key_for(request, bypass_cache);
+            if let Some(ref key) = key {
+                if let Some(mut cached) = cache.as_ref().and_then(|c| c.get(key)) {
+                    cached.id = Some(request.id);
+                    slots.push(Slot::Cached(cached));
+                    continue;
+                }
+            }
+            slots.push(Slot::Pending(request.id, dispatch(&state, request), key));
+        }
 
-    let mut request_line = request_str.clone();
-    if !request_line.ends_with('\n') {
-        request_line.push('\n');
+        let mut responses = Vec::with_capacity(slots.len());
+        for slot in slots {
+            match slot {
+                Slot::Cached(response) => responses.push(response),
+                Slot::Pending(id, rx, key) => {
+                    let response = await_response(&state, id, rx).await;
+                    if let Some(key) = key {
+                        if response.error.is_none() {
+                            if let Some(c) = cache.as_ref() {
+                                c.put(&key, &response);
+                            }
+                        }
+                    }
+                    responses.push(response);
+                }
+            }
+        }
+
+        return Ok(serde_json::to_string(&responses).unwrap());
     }
 
-    if let Err(e) = state.write(request_line.as_bytes()) {
-        state.pending_requests.lock().remove(&id);
-        let response = JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            result: None,
-            error: Some(JsonRpcError {
-                code: -32603,
-                message: e,
-                data: None,
-            }),
-            id: Some(id),
-        };
-        return Ok(serde_json::to_string(&response).unwrap());
-    }
-
-    match tokio::time::timeout(Duration::from_secs(30), rx).await {
-        Ok(Ok(response)) => Ok(serde_json::to_string(&response).unwrap()),
-        Ok(Err(_)) => {
-            let response = JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                result: None,
-                error: Some(JsonRpcError {
-                    code: -32603,
-                    message: "Request cancelled".to_string(),
-                    data: None,
-                }),
-                id: Some(id),
-            };
-            Ok(serde_json::to_string(&response).unwrap())
+    let request: JsonRpcRequest = serde_json::from_value(value).map_err(|e| {
+        serde_json::to_string(&error_response(
+            None,
+            ErrorKind::ParseError,
+            format!("Parse error: {}", e),
+        ))
+        .unwrap()
+    })?;
+    let id = request.id;
+    let cache_key = cache_key_for(&request, bypass_cache);
+    if let Some(ref key) = cache_key {
+        if let Some(mut cached) = cache.as_ref().and_then(|c| c.get(key)) {
+            cached.id = Some(id);
+            return Ok(serde_json::to_string(&cached).unwrap());
         }
-        Err(_) => {
-            state.pending_requests.lock().remove(&id);
-            let response = JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                result: None,
-                error: Some(JsonRpcError {
-                    code: -32603,
-                    message: "Request timeout".to_string(),
-                    data: None,
-                }),
-                id: Some(id),
-            };
-            Ok(serde_json::to_string(&response).unwrap())
+    }
+
+    let rx = dispatch(&state, &request);
+    let response = await_response(&state, id, rx).await;
+    if let Some(key) = cache_key {
+        if response.error.is_none() {
+            if let Some(c) = cache.as_ref() {
+                c.put(&key, &response);
+            }
         }
     }
+    Ok(serde_json::to_string(&response).unwrap())
+}
+
+#[tauri::command]
+pub async fn cancel_request(id: u64, state: State<'_, BackendState>) -> Result<bool, String> {
+    let cancelled = state.cancel_pending(id);
+    if cancelled {
+        let notification = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "$/cancelRequest",
+            "params": { "id": id },
+        });
+        let mut line = notification.to_string();
+        line.push('\n');
+        let _ = state.write(line.as_bytes());
+    }
+    Ok(cancelled)
+}
+
+#[tauri::command]
+pub async fn restart_backend(app: AppHandle, state: State<'_, BackendState>) -> Result<(), String> {
+    state.reset_restart_count();
+    crate::spawn_backend(&app)
+        .await
+        .map_err(|e| format!("Failed to restart backend: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_capabilities(state: State<'_, BackendState>) -> Result<CapabilitiesInfo, String> {
+    Ok(CapabilitiesInfo {
+        negotiated_version: state.negotiated_version(),
+        capabilities: state.capabilities(),
+        handshake_ok: state.is_handshake_ok(),
+    })
+}
+
+#[tauri::command]
+pub async fn cache_clear(cache: State<'_, Option<ResponseCache>>) -> Result<(), String> {
+    if let Some(c) = cache.as_ref() {
+        c.clear();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn cache_stats(
+    cache: State<'_, Option<ResponseCache>>,
+) -> Result<cache::CacheStats, String> {
+    Ok(cache
+        .as_ref()
+        .map(|c| c.stats())
+        .unwrap_or(cache::CacheStats {
+            entries: 0,
+            size_bytes: 0,
+        }))
+}
+
+#[tauri::command]
+pub async fn subscribe_stream(
+    stream_id: u64,
+    registry: State<'_, StreamRegistry>,
+) -> Result<(), String> {
+    registry.subscribe(stream_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unsubscribe_stream(
+    stream_id: u64,
+    registry: State<'_, StreamRegistry>,
+) -> Result<(), String> {
+    registry.unsubscribe(stream_id);
+    Ok(())
 }
 
 #[tauri::command]
@@ -123,37 +311,89 @@ pub async fn window_close(app: AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn write_text_file(path: String, contents: String) -> Result<(), String> {
-    std::fs::write(&path, contents).map_err(|e| format!("Failed to write file: {}", e))
+pub async fn write_text_file(path: String, contents: String) -> Result<(), crate::JsonRpcError> {
+    std::fs::write(&path, contents).map_err(|e| {
+        errors::rpc_error_from_io(
+            &e,
+            format!("Failed to write file: {}", e),
+            Some(serde_json::json!({ "path": path })),
+        )
+    })
 }
 
 #[tauri::command]
-pub async fn read_text_file(path: String) -> Result<String, String> {
-    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))
+pub async fn read_text_file(path: String) -> Result<String, crate::JsonRpcError> {
+    std::fs::read_to_string(&path).map_err(|e| {
+        errors::rpc_error_from_io(
+            &e,
+            format!("Failed to read file: {}", e),
+            Some(serde_json::json!({ "path": path })),
+        )
+    })
 }
 
 #[tauri::command]
-pub async fn open_path(path: String) -> Result<(), String> {
-    #[cfg(target_os = "windows")]
-    {
-        std::process::Command::new("explorer")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open path: {}", e))?;
-    }
-    #[cfg(target_os = "macos")]
-    {
-        std::process::Command::new("open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open path: {}", e))?;
-    }
-    #[cfg(target_os = "linux")]
-    {
-        std::process::Command::new("xdg-open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open path: {}", e))?;
+pub async fn open_path(path: String) -> Result<(), crate::JsonRpcError> {
+    let spawn_result = {
+        #[cfg(target_os = "windows")]
+        {
+            std::process::Command::new("explorer").arg(&path).spawn()
+        }
+        #[cfg(target_os = "macos")]
+        {
+            std::process::Command::new("open").arg(&path).spawn()
+        }
+        #[cfg(target_os = "linux")]
+        {
+            std::process::Command::new("xdg-open").arg(&path).spawn()
+        }
+    };
+    spawn_result.map(|_| ()).map_err(|e| {
+        errors::rpc_error_from_io(
+            &e,
+            format!("Failed to open path: {}", e),
+            Some(serde_json::json!({ "path": path })),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_method_advertised_allows_everything_before_capabilities_are_known() {
+        assert!(is_method_advertised(&[], "tts.synthesize"));
+    }
+
+    #[test]
+    fn is_method_advertised_checks_membership_once_known() {
+        let capabilities = vec!["tts.synthesize".to_string()];
+        assert!(is_method_advertised(&capabilities, "tts.synthesize"));
+        assert!(!is_method_advertised(&capabilities, "tts.describe"));
+    }
+
+    fn request(method: &str) -> JsonRpcRequest {
+        serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "id": 1,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cache_key_for_is_none_when_bypass_is_requested() {
+        assert!(cache_key_for(&request("tts.synthesize"), true).is_none());
+    }
+
+    #[test]
+    fn cache_key_for_is_none_for_an_uncacheable_method() {
+        assert!(cache_key_for(&request("tts.describe"), false).is_none());
+    }
+
+    #[test]
+    fn cache_key_for_is_some_for_a_cacheable_method() {
+        assert!(cache_key_for(&request("tts.synthesize"), false).is_some());
     }
-    Ok(())
 }
@@ -0,0 +1,159 @@
+//! Central mapping from concrete failure kinds (IO errors, backend lifecycle states, protocol
+//! errors) to stable JSON-RPC error codes and a machine-readable `data.kind` field, so the
+//! frontend can branch on error category instead of regex-matching English messages.
+
+use crate::JsonRpcError;
+use serde_json::json;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotFound,
+    PermissionDenied,
+    AlreadyExists,
+    ParseError,
+    BackendNotRunning,
+    HandshakeIncomplete,
+    MethodNotAdvertised,
+    Timeout,
+    Cancelled,
+    SpawnFailed,
+    Io,
+    Internal,
+}
+
+impl ErrorKind {
+    /// Stable JSON-RPC error code for this category. Codes never change meaning once shipped.
+    pub fn code(self) -> i32 {
+        match self {
+            ErrorKind::ParseError => -32700,
+            ErrorKind::MethodNotAdvertised => -32601,
+            ErrorKind::Internal => -32603,
+            ErrorKind::BackendNotRunning => -32001,
+            ErrorKind::HandshakeIncomplete => -32002,
+            ErrorKind::Cancelled => -32003,
+            ErrorKind::Timeout => -32004,
+            ErrorKind::SpawnFailed => -32005,
+            ErrorKind::NotFound => -32010,
+            ErrorKind::PermissionDenied => -32011,
+            ErrorKind::AlreadyExists => -32012,
+            ErrorKind::Io => -32013,
+        }
+    }
+
+    /// Machine-readable category string mirrored into `error.data.kind`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "NotFound",
+            ErrorKind::PermissionDenied => "PermissionDenied",
+            ErrorKind::AlreadyExists => "AlreadyExists",
+            ErrorKind::ParseError => "ParseError",
+            ErrorKind::BackendNotRunning => "BackendNotRunning",
+            ErrorKind::HandshakeIncomplete => "HandshakeIncomplete",
+            ErrorKind::MethodNotAdvertised => "MethodNotAdvertised",
+            ErrorKind::Timeout => "Timeout",
+            ErrorKind::Cancelled => "Cancelled",
+            ErrorKind::SpawnFailed => "SpawnFailed",
+            ErrorKind::Io => "Io",
+            ErrorKind::Internal => "Internal",
+        }
+    }
+}
+
+/// Classifies an [`std::io::Error`] into a stable [`ErrorKind`].
+pub fn classify_io_error(error: &std::io::Error) -> ErrorKind {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+        std::io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+        std::io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+        _ => ErrorKind::Io,
+    }
+}
+
+/// Builds a [`JsonRpcError`] for `kind`, merging `extra_data` fields alongside the category
+/// string so callers can branch on `error.data.kind` rather than message text.
+pub fn rpc_error(
+    kind: ErrorKind,
+    message: impl Into<String>,
+    extra_data: Option<serde_json::Value>,
+) -> JsonRpcError {
+    let mut data = json!({ "kind": kind.as_str() });
+    if let Some(extra_obj) = extra_data.as_ref().and_then(|v| v.as_object()) {
+        if let Some(obj) = data.as_object_mut() {
+            for (key, value) in extra_obj {
+                obj.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    JsonRpcError {
+        code: kind.code(),
+        message: message.into(),
+        data: Some(data),
+    }
+}
+
+/// Classifies `error` and builds the matching [`JsonRpcError`] in one step.
+pub fn rpc_error_from_io(
+    error: &std::io::Error,
+    message: impl Into<String>,
+    extra_data: Option<serde_json::Value>,
+) -> JsonRpcError {
+    rpc_error(classify_io_error(error), message, extra_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_io_error_maps_known_kinds() {
+        assert_eq!(
+            classify_io_error(&std::io::Error::from(std::io::ErrorKind::NotFound)),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            classify_io_error(&std::io::Error::from(std::io::ErrorKind::PermissionDenied)),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            classify_io_error(&std::io::Error::from(std::io::ErrorKind::AlreadyExists)),
+            ErrorKind::AlreadyExists
+        );
+    }
+
+    #[test]
+    fn classify_io_error_falls_back_to_io() {
+        assert_eq!(
+            classify_io_error(&std::io::Error::from(std::io::ErrorKind::TimedOut)),
+            ErrorKind::Io
+        );
+    }
+
+    #[test]
+    fn rpc_error_carries_the_kind_code_and_message() {
+        let error = rpc_error(ErrorKind::Timeout, "Request timeout", None);
+        assert_eq!(error.code, ErrorKind::Timeout.code());
+        assert_eq!(error.message, "Request timeout");
+        assert_eq!(
+            error.data.unwrap().get("kind").and_then(|v| v.as_str()),
+            Some("Timeout")
+        );
+    }
+
+    #[test]
+    fn rpc_error_merges_extra_data_alongside_kind() {
+        let error = rpc_error(
+            ErrorKind::MethodNotAdvertised,
+            "unsupported",
+            Some(json!({ "method": "tts.synthesize" })),
+        );
+        let data = error.data.unwrap();
+        assert_eq!(
+            data.get("kind").and_then(|v| v.as_str()),
+            Some("MethodNotAdvertised")
+        );
+        assert_eq!(
+            data.get("method").and_then(|v| v.as_str()),
+            Some("tts.synthesize")
+        );
+    }
+}
@@ -1,14 +1,32 @@
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use streaming::{StreamNotification, StreamRegistry};
 use tauri::{Emitter, Manager, WindowEvent};
-use tauri_plugin_shell::process::{CommandChild, CommandEvent};
-use tauri_plugin_shell::ShellExt;
 use tokio::sync::oneshot;
+use transport::{BackendTransport, TransportConfig, TransportEvent};
 
+mod cache;
 mod commands;
+mod errors;
+mod streaming;
+mod transport;
+
+/// Initial delay before the first restart attempt after a crash.
+const RESTART_INITIAL_BACKOFF_MS: u64 = 250;
+/// Upper bound the exponential backoff is capped at.
+const RESTART_MAX_BACKOFF_MS: u64 = 30_000;
+/// Give up automatic recovery once this many restarts in a row have crashed as well.
+const RESTART_MAX_ATTEMPTS: u32 = 10;
+
+/// Protocol version the frontend speaks. Only the major component needs to match the
+/// backend's for the handshake to succeed.
+const FRONTEND_PROTOCOL_VERSION: &str = "1.0";
+/// Timeout for the `initialize` handshake the frontend sends right after spawning.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcRequest {
@@ -46,15 +64,30 @@ pub struct DebugInfo {
     user_data_path: String,
     backend_error: Option<String>,
     backend_running: bool,
+    negotiated_version: Option<String>,
+    capabilities: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilitiesInfo {
+    negotiated_version: Option<String>,
+    capabilities: Vec<String>,
+    handshake_ok: bool,
 }
 
 type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<JsonRpcResponse>>>>;
 
 pub struct BackendState {
-    child: Mutex<Option<CommandChild>>,
+    child: Mutex<Option<Box<dyn BackendTransport>>>,
     pending_requests: PendingRequests,
     request_id: AtomicU64,
     backend_error: Mutex<Option<String>>,
+    backend_error_kind: Mutex<Option<errors::ErrorKind>>,
+    restart_count: AtomicU32,
+    last_exit_code: Mutex<Option<i32>>,
+    negotiated_version: Mutex<Option<String>>,
+    capabilities: Mutex<HashSet<String>>,
+    handshake_ok: AtomicBool,
 }
 
 impl BackendState {
@@ -64,6 +97,12 @@ impl BackendState {
             pending_requests: Arc::new(Mutex::new(HashMap::new())),
             request_id: AtomicU64::new(0),
             backend_error: Mutex::new(None),
+            backend_error_kind: Mutex::new(None),
+            restart_count: AtomicU32::new(0),
+            last_exit_code: Mutex::new(None),
+            negotiated_version: Mutex::new(None),
+            capabilities: Mutex::new(HashSet::new()),
+            handshake_ok: AtomicBool::new(false),
         }
     }
 
@@ -71,28 +110,43 @@ impl BackendState {
         self.request_id.fetch_add(1, Ordering::SeqCst)
     }
 
-    pub fn set_child(&self, child: CommandChild) {
-        *self.child.lock() = Some(child);
+    pub fn set_child(&self, transport: Box<dyn BackendTransport>) {
+        *self.child.lock() = Some(transport);
+    }
+
+    pub fn clear_child(&self) {
+        *self.child.lock() = None;
     }
 
     pub fn set_error(&self, error: String) {
         *self.backend_error.lock() = Some(error);
+        *self.backend_error_kind.lock() = None;
+    }
+
+    /// Like [`Self::set_error`], but tags the failure with an [`errors::ErrorKind`] so
+    /// `ipc_call` can report it through the matching JSON-RPC error code instead of the
+    /// generic `BackendNotRunning` fallback.
+    pub fn set_error_with_kind(&self, error: String, kind: errors::ErrorKind) {
+        *self.backend_error.lock() = Some(error);
+        *self.backend_error_kind.lock() = Some(kind);
     }
 
     pub fn get_error(&self) -> Option<String> {
         self.backend_error.lock().clone()
     }
 
+    pub fn error_kind(&self) -> Option<errors::ErrorKind> {
+        *self.backend_error_kind.lock()
+    }
+
     pub fn is_running(&self) -> bool {
         self.child.lock().is_some()
     }
 
     pub fn write(&self, data: &[u8]) -> Result<(), String> {
-        let mut guard = self.child.lock();
-        if let Some(ref mut child) = *guard {
-            child
-                .write(data)
-                .map_err(|e| format!("Failed to write to backend: {}", e))
+        let guard = self.child.lock();
+        if let Some(ref transport) = *guard {
+            transport.send(data)
         } else {
             Err("Backend not running".to_string())
         }
@@ -107,6 +161,103 @@ impl BackendState {
             let _ = sender.send(response);
         }
     }
+
+    /// Removes and returns the sender for `id`, if still pending, without resolving it.
+    pub fn take_pending(&self, id: u64) -> Option<oneshot::Sender<JsonRpcResponse>> {
+        self.pending_requests.lock().remove(&id)
+    }
+
+    /// Cancels an in-flight request: removes it from `pending_requests` and resolves the
+    /// caller's `oneshot` with a cancellation error distinct from a timeout or backend error.
+    /// Returns `false` if the request had already completed or didn't exist.
+    pub fn cancel_pending(&self, id: u64) -> bool {
+        if let Some(sender) = self.take_pending(id) {
+            let _ = sender.send(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                result: None,
+                error: Some(JsonRpcError {
+                    code: -32003,
+                    message: "Request cancelled".to_string(),
+                    data: None,
+                }),
+                id: Some(id),
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Immediately fail every in-flight request with `code`/`message` instead of letting
+    /// callers sit out the full `ipc_call` timeout after the backend has already died.
+    pub fn fail_all_pending(&self, code: i32, message: &str) {
+        let pending: Vec<_> = self.pending_requests.lock().drain().collect();
+        for (id, sender) in pending {
+            let _ = sender.send(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                result: None,
+                error: Some(JsonRpcError {
+                    code,
+                    message: message.to_string(),
+                    data: None,
+                }),
+                id: Some(id),
+            });
+        }
+    }
+
+    pub fn restart_count(&self) -> u32 {
+        self.restart_count.load(Ordering::SeqCst)
+    }
+
+    pub fn reset_restart_count(&self) {
+        self.restart_count.store(0, Ordering::SeqCst);
+    }
+
+    pub fn set_last_exit_code(&self, code: Option<i32>) {
+        *self.last_exit_code.lock() = code;
+    }
+
+    pub fn last_exit_code(&self) -> Option<i32> {
+        *self.last_exit_code.lock()
+    }
+
+    /// Resets the negotiated handshake state; called each time the backend (re)spawns so a
+    /// stale negotiation from a previous process can't leak into the new one.
+    pub fn reset_handshake(&self) {
+        *self.negotiated_version.lock() = None;
+        self.capabilities.lock().clear();
+        self.handshake_ok.store(false, Ordering::SeqCst);
+    }
+
+    /// Records a completed handshake and treats it as proof the backend is healthy again: a
+    /// crash loop counter that only reset on a manual `restart_backend` would otherwise keep
+    /// accumulating across crashes that are weeks apart with long healthy uptime in between,
+    /// eventually tripping `RESTART_MAX_ATTEMPTS` even though the backend has been fine.
+    pub fn set_negotiated(&self, version: String, capabilities: HashSet<String>) {
+        *self.negotiated_version.lock() = Some(version);
+        *self.capabilities.lock() = capabilities;
+        self.handshake_ok.store(true, Ordering::SeqCst);
+        self.reset_restart_count();
+    }
+
+    pub fn is_handshake_ok(&self) -> bool {
+        self.handshake_ok.load(Ordering::SeqCst)
+    }
+
+    pub fn negotiated_version(&self) -> Option<String> {
+        self.negotiated_version.lock().clone()
+    }
+
+    pub fn capabilities(&self) -> Vec<String> {
+        let mut caps: Vec<String> = self.capabilities.lock().iter().cloned().collect();
+        caps.sort();
+        caps
+    }
+
+    pub fn has_capability(&self, method: &str) -> bool {
+        self.capabilities.lock().contains(method)
+    }
 }
 
 impl Default for BackendState {
@@ -115,90 +266,224 @@ impl Default for BackendState {
     }
 }
 
-fn spawn_backend(app: &tauri::AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+pub(crate) async fn spawn_backend(
+    app: &tauri::AppHandle,
+) -> Result<(), Box<dyn std::error::Error>> {
     let state = app.state::<BackendState>();
-    let shell = app.shell();
 
     // Log current directory for debugging
     if let Ok(cwd) = std::env::current_dir() {
         log::info!("Current working directory: {:?}", cwd);
     }
 
-    let sidecar = shell.sidecar("backend").map_err(|e| {
-        log::error!("Failed to create sidecar command: {} - Make sure backend-x86_64-pc-windows-msvc.exe exists", e);
-        e
-    })?;
-
-    let (mut rx, child) = sidecar.spawn().map_err(|e| {
-        let error = format!("Failed to spawn backend: {}", e);
+    let config = TransportConfig::from_env();
+    let (transport, mut rx) = transport::spawn(app, &config).await.map_err(|e| {
+        let error = format!("Failed to start backend transport: {}", e);
         log::error!("{}", error);
-        state.set_error(error.clone());
+        state.set_error_with_kind(error.clone(), errors::ErrorKind::SpawnFailed);
         e
     })?;
 
-    state.set_child(child);
-    log::info!("Backend sidecar spawned successfully");
+    state.reset_handshake();
+    state.set_child(transport);
+    log::info!("Backend transport ready ({:?})", config);
 
     let app_handle = app.clone();
     let pending = state.pending_requests.clone();
 
     tauri::async_runtime::spawn(async move {
-        let mut buffer = String::new();
-
         while let Some(event) = rx.recv().await {
             match event {
-                CommandEvent::Stdout(line_bytes) => {
-                    let line = String::from_utf8_lossy(&line_bytes);
-                    buffer.push_str(&line);
-
-                    while let Some(newline_pos) = buffer.find('\n') {
-                        let json_line = buffer[..newline_pos].trim().to_string();
-                        buffer = buffer[newline_pos + 1..].to_string();
-
-                        if json_line.is_empty() {
-                            continue;
-                        }
-
-                        match serde_json::from_str::<JsonRpcResponse>(&json_line) {
-                            Ok(response) => {
-                                if let Some(id) = response.id {
-                                    if let Some(sender) = pending.lock().remove(&id) {
-                                        let _ = sender.send(response);
-                                    }
-                                } else if let Some(ref result) = response.result {
-                                    if let Some(method) = result.get("method").and_then(|m| m.as_str()) {
-                                        let _ = app_handle.emit("backend-event", serde_json::json!({
+                TransportEvent::Message(json_line) => {
+                    match serde_json::from_str::<JsonRpcResponse>(&json_line) {
+                        Ok(response) => {
+                            if let Some(id) = response.id {
+                                if let Some(sender) = pending.lock().remove(&id) {
+                                    let _ = sender.send(response);
+                                }
+                            } else if let Some(ref result) = response.result {
+                                if let Ok(notification) =
+                                    serde_json::from_value::<StreamNotification>(result.clone())
+                                {
+                                    app_handle
+                                        .state::<StreamRegistry>()
+                                        .ingest(&app_handle, notification);
+                                } else if let Some(method) =
+                                    result.get("method").and_then(|m| m.as_str())
+                                {
+                                    let _ = app_handle.emit(
+                                        "backend-event",
+                                        serde_json::json!({
                                             "method": method,
                                             "params": result.get("params")
-                                        }));
-                                    }
+                                        }),
+                                    );
                                 }
                             }
-                            Err(e) => {
-                                log::warn!("Failed to parse backend response: {} - {}", e, json_line);
-                            }
+                        }
+                        Err(e) => {
+                            log::warn!("Failed to parse backend response: {} - {}", e, json_line);
                         }
                     }
                 }
-                CommandEvent::Stderr(line_bytes) => {
-                    let line = String::from_utf8_lossy(&line_bytes);
+                TransportEvent::Stderr(line) => {
                     log::warn!("Backend stderr: {}", line);
                 }
-                CommandEvent::Error(error) => {
+                TransportEvent::Error(error) => {
                     log::error!("Backend error: {}", error);
                 }
-                CommandEvent::Terminated(payload) => {
-                    log::info!("Backend terminated with code: {:?}", payload.code);
+                TransportEvent::Terminated(code) => {
+                    log::info!("Backend terminated with code: {:?}", code);
+                    handle_backend_terminated(app_handle.clone(), code);
                     break;
                 }
-                _ => {}
             }
         }
     });
 
+    let handshake_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = negotiate_handshake(&handshake_app).await {
+            log::error!("Backend handshake failed: {}", e);
+            handshake_app.state::<BackendState>().set_error(e);
+        }
+    });
+
     Ok(())
 }
 
+/// Sends the reserved `initialize` request right after spawn and blocks `ipc_call` from
+/// accepting user traffic until the backend has replied with a compatible protocol version.
+async fn negotiate_handshake(app: &tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<BackendState>();
+    let id = state.next_id();
+    let (tx, rx) = oneshot::channel();
+    state.add_pending(id, tx);
+
+    let request = JsonRpcRequest {
+        jsonrpc: "2.0".to_string(),
+        method: "initialize".to_string(),
+        params: Some(serde_json::json!({ "protocolVersion": FRONTEND_PROTOCOL_VERSION })),
+        id,
+    };
+    let mut line = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    state.write(line.as_bytes())?;
+
+    let response = match tokio::time::timeout(HANDSHAKE_TIMEOUT, rx).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(_)) => return Err("Handshake cancelled before the backend replied".to_string()),
+        Err(_) => {
+            state.pending_requests.lock().remove(&id);
+            return Err(
+                "Backend did not respond to initialize within the handshake timeout".to_string(),
+            );
+        }
+    };
+
+    if let Some(error) = response.error {
+        return Err(format!("Backend rejected initialize: {}", error.message));
+    }
+
+    let result = response
+        .result
+        .ok_or_else(|| "Backend initialize response had no result".to_string())?;
+    let backend_version = result
+        .get("protocolVersion")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| "Backend initialize response missing protocolVersion".to_string())?
+        .to_string();
+    let capabilities: HashSet<String> = result
+        .get("capabilities")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if protocol_major(&backend_version) != protocol_major(FRONTEND_PROTOCOL_VERSION) {
+        return Err(format!(
+            "Incompatible backend protocol version: frontend={}, backend={}",
+            FRONTEND_PROTOCOL_VERSION, backend_version
+        ));
+    }
+
+    log::info!(
+        "Backend handshake complete: version={}, capabilities={}",
+        backend_version,
+        capabilities.len()
+    );
+    state.set_negotiated(backend_version, capabilities);
+    Ok(())
+}
+
+fn protocol_major(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
+
+/// Exponential backoff for restart attempt number `attempt` (1-indexed), capped at
+/// `RESTART_MAX_BACKOFF_MS`.
+fn restart_backoff_ms(attempt: u32) -> u64 {
+    RESTART_INITIAL_BACKOFF_MS
+        .saturating_mul(1u64 << (attempt - 1).min(20))
+        .min(RESTART_MAX_BACKOFF_MS)
+}
+
+/// Reacts to the sidecar dying: unwedges anyone waiting on `ipc_call` or subscribed to a
+/// stream, then schedules a restart with capped exponential backoff so a crash loop can't
+/// busy-spin the backend.
+fn handle_backend_terminated(app: tauri::AppHandle, exit_code: Option<i32>) {
+    let state = app.state::<BackendState>();
+    state.clear_child();
+    state.set_last_exit_code(exit_code);
+    state.fail_all_pending(-32001, "Backend process terminated");
+    app.state::<StreamRegistry>()
+        .fail_all(&app, "Backend process terminated");
+
+    let attempt = state.restart_count.fetch_add(1, Ordering::SeqCst) + 1;
+    let _ = app.emit(
+        "backend-crashed",
+        serde_json::json!({ "exitCode": exit_code, "attempt": attempt }),
+    );
+
+    if attempt > RESTART_MAX_ATTEMPTS {
+        let message = format!(
+            "Backend crashed {} times in a row; giving up automatic restart",
+            attempt
+        );
+        log::error!("{}", message);
+        state.set_error(message);
+        return;
+    }
+
+    let backoff_ms = restart_backoff_ms(attempt);
+    log::warn!(
+        "Restarting backend in {}ms (attempt {}/{})",
+        backoff_ms,
+        attempt,
+        RESTART_MAX_ATTEMPTS
+    );
+
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(backoff_ms)).await;
+        match spawn_backend(&app).await {
+            Ok(()) => {
+                let _ = app.emit(
+                    "backend-restarted",
+                    serde_json::json!({ "attempt": attempt }),
+                );
+            }
+            Err(e) => {
+                let message = format!("Restart attempt {} failed: {}", attempt, e);
+                log::error!("{}", message);
+                app.state::<BackendState>().set_error(message);
+            }
+        }
+    });
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -212,6 +497,7 @@ pub fn run() {
             }
         }))
         .manage(BackendState::new())
+        .manage(StreamRegistry::new())
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -221,7 +507,32 @@ pub fn run() {
                 )?;
             }
 
-            if let Err(e) = spawn_backend(app.handle()) {
+            // The response cache is best-effort: caching must never be a hard dependency for
+            // core IPC traffic, so a failure to create the data dir or open the store just
+            // means `ipc_call` runs uncached instead of failing outright.
+            let response_cache = match app.path().app_local_data_dir() {
+                Ok(data_dir) => {
+                    if let Err(e) = std::fs::create_dir_all(&data_dir) {
+                        log::error!("Failed to create app data dir for response cache: {}", e);
+                        None
+                    } else {
+                        match cache::ResponseCache::open(&data_dir) {
+                            Ok(response_cache) => Some(response_cache),
+                            Err(e) => {
+                                log::error!("Failed to open response cache: {}", e);
+                                None
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::error!("Failed to resolve app data dir for response cache: {}", e);
+                    None
+                }
+            };
+            app.manage(response_cache);
+
+            if let Err(e) = tauri::async_runtime::block_on(spawn_backend(app.handle())) {
                 log::error!("Failed to spawn backend: {}", e);
             }
 
@@ -247,6 +558,8 @@ pub fn run() {
                     .unwrap_or_default(),
                 backend_error: state.get_error(),
                 backend_running: state.is_running(),
+                negotiated_version: state.negotiated_version(),
+                capabilities: state.capabilities(),
             };
 
             if let Some(window) = app.get_webview_window("main") {
@@ -275,7 +588,46 @@ pub fn run() {
             commands::write_text_file,
             commands::read_text_file,
             commands::open_path,
+            commands::restart_backend,
+            commands::get_capabilities,
+            commands::cancel_request,
+            commands::cache_clear,
+            commands::cache_stats,
+            commands::subscribe_stream,
+            commands::unsubscribe_stream,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restart_backoff_doubles_until_the_cap() {
+        assert_eq!(restart_backoff_ms(1), RESTART_INITIAL_BACKOFF_MS);
+        assert_eq!(restart_backoff_ms(2), RESTART_INITIAL_BACKOFF_MS * 2);
+        assert_eq!(restart_backoff_ms(3), RESTART_INITIAL_BACKOFF_MS * 4);
+    }
+
+    #[test]
+    fn restart_backoff_saturates_at_the_max() {
+        assert_eq!(
+            restart_backoff_ms(RESTART_MAX_ATTEMPTS),
+            RESTART_MAX_BACKOFF_MS
+        );
+        assert_eq!(restart_backoff_ms(1000), RESTART_MAX_BACKOFF_MS);
+    }
+
+    #[test]
+    fn protocol_major_takes_the_leading_component() {
+        assert_eq!(protocol_major("1.0"), "1");
+        assert_eq!(protocol_major("2.3.1"), "2");
+    }
+
+    #[test]
+    fn protocol_major_falls_back_to_the_whole_string_without_a_dot() {
+        assert_eq!(protocol_major("1"), "1");
+    }
+}
@@ -0,0 +1,262 @@
+//! Ordered delivery for streamed backend notifications (e.g. incremental synthesis audio or
+//! progress). The backend tags each chunk with the `stream_id` of the request that opened the
+//! stream plus a `seq`; chunks can arrive out of order over the transport, so each stream keeps
+//! a small buffer until the next expected `seq` shows up before forwarding it to the window.
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Shape of a streamed backend notification. Distinguished from a plain `backend-event` by
+/// carrying `stream_id`/`seq` instead of a bare `method`/`params` pair.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamNotification {
+    pub stream_id: u64,
+    pub seq: u64,
+    pub chunk: serde_json::Value,
+    #[serde(rename = "final", default)]
+    pub is_final: bool,
+}
+
+/// Caps how many chunks a single stream may buffer while waiting for a gap to fill. Without a
+/// cap, a single dropped chunk over the transport would make `next_seq` unreachable forever and
+/// the buffer would grow without bound for the rest of the stream's lifetime.
+const MAX_PENDING_CHUNKS: usize = 256;
+
+struct StreamBuffer {
+    /// Seeded from the `seq` of the first chunk seen for this stream rather than assumed to be
+    /// `0`, since nothing guarantees the backend's sequence numbers for a given stream start at
+    /// exactly `0`.
+    next_seq: Option<u64>,
+    final_seq: Option<u64>,
+    pending: BTreeMap<u64, serde_json::Value>,
+}
+
+impl StreamBuffer {
+    fn new() -> Self {
+        Self {
+            next_seq: None,
+            final_seq: None,
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+enum Outcome {
+    Continue,
+    Finished,
+    Stalled,
+}
+
+/// A chunk that is now contiguous with the last one delivered and ready to emit, in order.
+struct Delivery {
+    seq: u64,
+    chunk: serde_json::Value,
+}
+
+/// The pure reordering/buffering step of [`StreamRegistry::ingest`], split out so it can be
+/// unit-tested without a real `tauri::AppHandle` to emit through.
+fn advance_buffer(
+    buffer: &mut StreamBuffer,
+    seq: u64,
+    chunk: serde_json::Value,
+    is_final: bool,
+) -> (Vec<Delivery>, Outcome) {
+    let next_seq = *buffer.next_seq.get_or_insert(seq);
+    buffer.pending.insert(seq, chunk);
+    if is_final {
+        buffer.final_seq = Some(seq);
+    }
+
+    if buffer.pending.len() > MAX_PENDING_CHUNKS {
+        return (Vec::new(), Outcome::Stalled);
+    }
+
+    let mut deliveries = Vec::new();
+    let mut cursor = next_seq;
+    let mut outcome = Outcome::Continue;
+    while let Some(chunk) = buffer.pending.remove(&cursor) {
+        let is_final_chunk = buffer.final_seq == Some(cursor);
+        deliveries.push(Delivery { seq: cursor, chunk });
+        cursor += 1;
+        if is_final_chunk {
+            outcome = Outcome::Finished;
+            break;
+        }
+    }
+    buffer.next_seq = Some(cursor);
+    (deliveries, outcome)
+}
+
+/// Tracks which streams the window has subscribed to and reorders their chunks by `seq`.
+/// Mirrors the `pending_requests` map on [`crate::BackendState`]: a plain `Mutex`-guarded map
+/// keyed by the thing the backend tags its messages with, rather than a real channel.
+pub struct StreamRegistry {
+    streams: Mutex<HashMap<u64, StreamBuffer>>,
+}
+
+impl StreamRegistry {
+    pub fn new() -> Self {
+        Self {
+            streams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts buffering chunks for `stream_id`. Re-subscribing resets any partial progress.
+    pub fn subscribe(&self, stream_id: u64) {
+        self.streams.lock().insert(stream_id, StreamBuffer::new());
+    }
+
+    /// Drops all buffered state for `stream_id`, discarding any chunks still in flight.
+    pub fn unsubscribe(&self, stream_id: u64) {
+        self.streams.lock().remove(&stream_id);
+    }
+
+    /// Fails every currently-subscribed stream with `message` and drops their buffers. Called
+    /// when the backend dies so a window mid-stream gets a terminal signal instead of hanging
+    /// forever waiting for a `stream-chunk`/`stream-end` that will never arrive — the same
+    /// wedged-caller failure mode `fail_all_pending` exists to avoid for `ipc_call`.
+    pub fn fail_all(&self, app: &tauri::AppHandle, message: &str) {
+        use tauri::Emitter;
+
+        let stream_ids: Vec<u64> = {
+            let mut streams = self.streams.lock();
+            let ids = streams.keys().copied().collect();
+            streams.clear();
+            ids
+        };
+        for stream_id in stream_ids {
+            let _ = app.emit(
+                "stream-error",
+                serde_json::json!({ "streamId": stream_id, "message": message }),
+            );
+        }
+    }
+
+    /// Buffers `notification` and emits every chunk that is now contiguous with the last one
+    /// delivered, in order. Emits `stream-end` and drops the stream's state once the chunk
+    /// tagged `final` has been delivered. Notifications for a `stream_id` nobody subscribed to
+    /// are dropped, since there's no window waiting to render them. If the gap to `next_seq`
+    /// never closes (a chunk was lost over the transport), the buffer is bounded: once it holds
+    /// more than `MAX_PENDING_CHUNKS` the stream is failed with a `stream-error` instead of
+    /// buffering indefinitely.
+    pub fn ingest(&self, app: &tauri::AppHandle, notification: StreamNotification) {
+        use tauri::Emitter;
+
+        let mut streams = self.streams.lock();
+        let Some(buffer) = streams.get_mut(&notification.stream_id) else {
+            log::debug!(
+                "Dropping chunk for unsubscribed stream {}",
+                notification.stream_id
+            );
+            return;
+        };
+
+        let (deliveries, outcome) = advance_buffer(
+            buffer,
+            notification.seq,
+            notification.chunk,
+            notification.is_final,
+        );
+
+        for delivery in deliveries {
+            let _ = app.emit(
+                "stream-chunk",
+                serde_json::json!({
+                    "streamId": notification.stream_id,
+                    "seq": delivery.seq,
+                    "chunk": delivery.chunk,
+                }),
+            );
+        }
+
+        match outcome {
+            Outcome::Continue => {}
+            Outcome::Finished => {
+                let _ = app.emit(
+                    "stream-end",
+                    serde_json::json!({ "streamId": notification.stream_id }),
+                );
+                streams.remove(&notification.stream_id);
+            }
+            Outcome::Stalled => {
+                let _ = app.emit(
+                    "stream-error",
+                    serde_json::json!({
+                        "streamId": notification.stream_id,
+                        "message": "stream buffer overflowed waiting for a missing chunk",
+                    }),
+                );
+                streams.remove(&notification.stream_id);
+            }
+        }
+    }
+}
+
+impl Default for StreamRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seqs(deliveries: &[Delivery]) -> Vec<u64> {
+        deliveries.iter().map(|d| d.seq).collect()
+    }
+
+    #[test]
+    fn delivers_in_order_despite_out_of_order_arrival() {
+        let mut buffer = StreamBuffer::new();
+
+        let (delivered, _) = advance_buffer(&mut buffer, 2, serde_json::json!("c"), false);
+        assert!(delivered.is_empty());
+
+        let (delivered, _) = advance_buffer(&mut buffer, 0, serde_json::json!("a"), false);
+        assert_eq!(seqs(&delivered), vec![0]);
+
+        let (delivered, _) = advance_buffer(&mut buffer, 1, serde_json::json!("b"), false);
+        assert_eq!(seqs(&delivered), vec![1, 2]);
+    }
+
+    #[test]
+    fn seeds_next_seq_from_the_first_chunk_seen() {
+        let mut buffer = StreamBuffer::new();
+
+        let (delivered, _) = advance_buffer(&mut buffer, 100, serde_json::json!("a"), false);
+        assert_eq!(seqs(&delivered), vec![100]);
+
+        let (delivered, _) = advance_buffer(&mut buffer, 101, serde_json::json!("b"), true);
+        assert_eq!(seqs(&delivered), vec![101]);
+    }
+
+    #[test]
+    fn final_chunk_reports_finished_once_delivered() {
+        let mut buffer = StreamBuffer::new();
+        let (_, outcome) = advance_buffer(&mut buffer, 0, serde_json::json!("a"), true);
+        assert!(matches!(outcome, Outcome::Finished));
+    }
+
+    #[test]
+    fn non_final_chunk_reports_continue() {
+        let mut buffer = StreamBuffer::new();
+        let (_, outcome) = advance_buffer(&mut buffer, 0, serde_json::json!("a"), false);
+        assert!(matches!(outcome, Outcome::Continue));
+    }
+
+    #[test]
+    fn overflowing_the_buffer_reports_stalled() {
+        let mut buffer = StreamBuffer::new();
+        // Expect seq 0 but never send it, so every later chunk stays buffered behind the gap.
+        buffer.next_seq = Some(0);
+
+        let mut outcome = Outcome::Continue;
+        for seq in 1..=(MAX_PENDING_CHUNKS as u64 + 1) {
+            let (_, this_outcome) = advance_buffer(&mut buffer, seq, serde_json::json!(seq), false);
+            outcome = this_outcome;
+        }
+        assert!(matches!(outcome, Outcome::Stalled));
+    }
+}
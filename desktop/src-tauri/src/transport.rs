@@ -0,0 +1,266 @@
+//! Abstracts how the frontend talks to the TTS backend behind a single trait so the rest of
+//! the app (handshake, `ipc_call`, the supervisor) doesn't care whether the backend is a
+//! spawned sidecar talking newline-delimited JSON over stdio, or an already-running process
+//! reachable over a WebSocket.
+
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// A transport-level event, independent of whether the backend is a local sidecar or a
+/// remote WebSocket endpoint. One `Message` per complete JSON-RPC text payload.
+#[derive(Debug)]
+pub enum TransportEvent {
+    Message(String),
+    Stderr(String),
+    Error(String),
+    Terminated(Option<i32>),
+}
+
+/// A channel to a running backend. Implementations only need to frame and deliver bytes;
+/// demuxing JSON-RPC ids back to callers stays in `lib.rs`.
+pub trait BackendTransport: Send + Sync {
+    fn send(&self, data: &[u8]) -> Result<(), String>;
+}
+
+/// Selects which transport to use. Read from `TTS_BACKEND_TRANSPORT` so a dev/ops setup can
+/// point the frontend at an already-running backend instead of spawning the bundled sidecar.
+#[derive(Debug, Clone)]
+pub enum TransportConfig {
+    Stdio,
+    WebSocket { url: String },
+}
+
+impl TransportConfig {
+    pub fn from_env() -> Self {
+        match std::env::var("TTS_BACKEND_TRANSPORT").ok().as_deref() {
+            Some("websocket") | Some("ws") => {
+                let url = std::env::var("TTS_BACKEND_WS_URL")
+                    .unwrap_or_else(|_| "ws://127.0.0.1:8765".to_string());
+                TransportConfig::WebSocket { url }
+            }
+            _ => TransportConfig::Stdio,
+        }
+    }
+}
+
+/// Spawns the bundled sidecar and returns a [`BackendTransport`] plus the event stream that
+/// demuxes its stdout into one [`TransportEvent::Message`] per newline-delimited JSON-RPC line.
+pub struct StdioTransport {
+    child: Mutex<CommandChild>,
+}
+
+impl StdioTransport {
+    pub fn spawn(
+        app: &tauri::AppHandle,
+    ) -> Result<(Self, mpsc::Receiver<TransportEvent>), Box<dyn std::error::Error>> {
+        let shell = app.shell();
+        let sidecar = shell.sidecar("backend").map_err(|e| {
+            log::error!(
+                "Failed to create sidecar command: {} - Make sure backend-x86_64-pc-windows-msvc.exe exists",
+                e
+            );
+            e
+        })?;
+        let (mut rx, child) = sidecar.spawn()?;
+
+        let (tx, out_rx) = mpsc::channel(256);
+        tauri::async_runtime::spawn(async move {
+            let mut buffer = String::new();
+
+            while let Some(event) = rx.recv().await {
+                match event {
+                    CommandEvent::Stdout(bytes) => {
+                        buffer.push_str(&String::from_utf8_lossy(&bytes));
+                        while let Some(pos) = buffer.find('\n') {
+                            let line = buffer[..pos].trim().to_string();
+                            buffer = buffer[pos + 1..].to_string();
+                            if !line.is_empty() {
+                                let _ = tx.send(TransportEvent::Message(line)).await;
+                            }
+                        }
+                    }
+                    CommandEvent::Stderr(bytes) => {
+                        let line = String::from_utf8_lossy(&bytes).to_string();
+                        let _ = tx.send(TransportEvent::Stderr(line)).await;
+                    }
+                    CommandEvent::Error(error) => {
+                        let _ = tx.send(TransportEvent::Error(error)).await;
+                    }
+                    CommandEvent::Terminated(payload) => {
+                        let _ = tx.send(TransportEvent::Terminated(payload.code)).await;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        Ok((
+            Self {
+                child: Mutex::new(child),
+            },
+            out_rx,
+        ))
+    }
+}
+
+impl BackendTransport for StdioTransport {
+    fn send(&self, data: &[u8]) -> Result<(), String> {
+        self.child
+            .lock()
+            .write(data)
+            .map_err(|e| format!("Failed to write to backend: {}", e))
+    }
+}
+
+/// Translates one inbound WebSocket frame (or `None`, meaning the stream ended) into the
+/// transport events to emit and whether the reader loop should stop. A clean close, a read
+/// error, and the stream simply running out all mean the same thing to the rest of the app —
+/// the connection is gone — so every one of them must produce a `Terminated` event; otherwise
+/// `spawn_backend`'s demux loop just sees its channel close with nothing on it and
+/// `handle_backend_terminated` (and therefore the chunk0-1 auto-restart) never runs. Split out
+/// from the reader task so this mapping can be unit-tested without a real WebSocket connection,
+/// mirroring the `advance_buffer` split in `streaming.rs`.
+fn translate_ws_message(
+    message: Option<Result<WsMessage, tokio_tungstenite::tungstenite::Error>>,
+) -> (Vec<TransportEvent>, bool) {
+    match message {
+        Some(Ok(WsMessage::Text(text))) => (vec![TransportEvent::Message(text)], false),
+        Some(Ok(WsMessage::Close(_))) => (vec![TransportEvent::Terminated(None)], true),
+        Some(Ok(_)) => (Vec::new(), false),
+        Some(Err(e)) => (
+            vec![
+                TransportEvent::Error(e.to_string()),
+                TransportEvent::Terminated(None),
+            ],
+            true,
+        ),
+        None => (vec![TransportEvent::Terminated(None)], true),
+    }
+}
+
+/// Connects to an already-running backend over WebSocket, framing each JSON-RPC message as a
+/// text frame and demuxing inbound frames into the same [`TransportEvent::Message`] shape the
+/// stdio transport produces, so the reader loop in `lib.rs` doesn't need to know the difference.
+pub struct WebSocketTransport {
+    outbound: mpsc::UnboundedSender<String>,
+}
+
+impl WebSocketTransport {
+    pub async fn connect(
+        url: &str,
+    ) -> Result<(Self, mpsc::Receiver<TransportEvent>), Box<dyn std::error::Error>> {
+        let (ws_stream, _) = tokio_tungstenite::connect_async(url).await?;
+        let (mut write, mut read) = ws_stream.split();
+
+        let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel::<String>();
+        let (event_tx, event_rx) = mpsc::channel(256);
+
+        tauri::async_runtime::spawn(async move {
+            while let Some(line) = outbound_rx.recv().await {
+                if write.send(WsMessage::Text(line)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let reader_events = event_tx.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let (events, should_stop) = translate_ws_message(read.next().await);
+                for event in events {
+                    let _ = reader_events.send(event).await;
+                }
+                if should_stop {
+                    break;
+                }
+            }
+        });
+
+        Ok((
+            Self {
+                outbound: outbound_tx,
+            },
+            event_rx,
+        ))
+    }
+}
+
+impl BackendTransport for WebSocketTransport {
+    fn send(&self, data: &[u8]) -> Result<(), String> {
+        let text = String::from_utf8_lossy(data).trim_end().to_string();
+        self.outbound
+            .send(text)
+            .map_err(|_| "WebSocket transport is closed".to_string())
+    }
+}
+
+/// Spawns or connects the transport selected by `config`.
+pub async fn spawn(
+    app: &tauri::AppHandle,
+    config: &TransportConfig,
+) -> Result<(Box<dyn BackendTransport>, mpsc::Receiver<TransportEvent>), Box<dyn std::error::Error>>
+{
+    match config {
+        TransportConfig::Stdio => {
+            let (transport, rx) = StdioTransport::spawn(app)?;
+            Ok((Box::new(transport), rx))
+        }
+        TransportConfig::WebSocket { url } => {
+            let (transport, rx) = WebSocketTransport::connect(url).await?;
+            Ok((Box::new(transport), rx))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_frame_yields_a_message_and_keeps_reading() {
+        let (events, should_stop) =
+            translate_ws_message(Some(Ok(WsMessage::Text("hi".to_string()))));
+        assert!(matches!(events.as_slice(), [TransportEvent::Message(s)] if s == "hi"));
+        assert!(!should_stop);
+    }
+
+    #[test]
+    fn close_frame_yields_terminated_and_stops() {
+        let (events, should_stop) = translate_ws_message(Some(Ok(WsMessage::Close(None))));
+        assert!(matches!(
+            events.as_slice(),
+            [TransportEvent::Terminated(None)]
+        ));
+        assert!(should_stop);
+    }
+
+    #[test]
+    fn read_error_yields_error_then_terminated_and_stops() {
+        let (events, should_stop) = translate_ws_message(Some(Err(
+            tokio_tungstenite::tungstenite::Error::ConnectionClosed,
+        )));
+        assert!(matches!(
+            events.as_slice(),
+            [TransportEvent::Error(_), TransportEvent::Terminated(None)]
+        ));
+        assert!(should_stop);
+    }
+
+    #[test]
+    fn end_of_stream_yields_terminated_and_stops() {
+        // The backend dropping the socket without a close frame ends the stream with `None`
+        // rather than an `Err` or a `Close` message; this must still be treated as a
+        // disconnect, not silently ignored.
+        let (events, should_stop) = translate_ws_message(None);
+        assert!(matches!(
+            events.as_slice(),
+            [TransportEvent::Terminated(None)]
+        ));
+        assert!(should_stop);
+    }
+}